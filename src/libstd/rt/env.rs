@@ -0,0 +1,116 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runtime configuration, read once from environment variables at
+//! startup and threaded down into `rt::run`.
+
+use from_str::from_str;
+use option::{Option, Some, None};
+use os;
+use rt::rtio::EventLoopFactory;
+use rt::uv::uvio::UvEventLoopFactory;
+
+/// The rtio backends `RUST_RTIO` can select between.
+#[deriving(Eq)]
+enum RtioBackend {
+    Uv,
+    Null,
+}
+
+/// Parses a `RUST_RTIO` value; anything other than `"null"`, including
+/// it being unset, keeps the libuv default.
+fn parse_rtio_backend(v: Option<&str>) -> RtioBackend {
+    match v {
+        Some(s) if "null" == s => Null,
+        _ => Uv,
+    }
+}
+
+/// Selects which `EventLoopFactory` each scheduler should be driven by.
+///
+/// Controlled by `RUST_RTIO`: `"uv"` (the default) selects the libuv
+/// backend, `"null"` selects a no-I/O loop for compute-only workloads
+/// that don't want the libuv dependency.
+pub fn event_loop_factory() -> ~EventLoopFactory {
+    use rt::rtio::NullEventLoopFactory;
+
+    let rtio = os::getenv("RUST_RTIO");
+    match parse_rtio_backend(rtio.as_ref().map(|s| s.as_slice())) {
+        Uv => ~UvEventLoopFactory as ~EventLoopFactory,
+        Null => ~NullEventLoopFactory as ~EventLoopFactory,
+    }
+}
+
+/// Scheduler thread topology, read once at startup and handed down into
+/// `rt::run` as a single config value rather than re-read per-thread.
+pub struct SchedConfig {
+    /// Number of scheduler threads to run. `None` means use
+    /// `util::default_sched_threads()`, one per core.
+    nthreads: Option<uint>,
+    /// Whether to pin each scheduler thread to its own core.
+    affinity: bool,
+}
+
+/// Reads the scheduler thread-count override (`RUST_THREADS`) and the
+/// CPU-affinity policy (`RUST_SCHED_AFFINITY`) from the environment.
+///
+/// `RUST_THREADS=0` (or any unparseable value) is treated the same as
+/// not setting it at all -- `rt::run` always needs at least one
+/// scheduler to enqueue the main task onto, so a zero override here
+/// would otherwise surface as an empty-vector index panic at startup.
+pub fn sched_config() -> SchedConfig {
+    let nthreads = os::getenv("RUST_THREADS").and_then(|s| parse_nthreads(s));
+    let affinity = match os::getenv("RUST_SCHED_AFFINITY") {
+        Some(ref s) => *s != ~"0",
+        None => false,
+    };
+    SchedConfig { nthreads: nthreads, affinity: affinity }
+}
+
+/// Parses a `RUST_THREADS` value, rejecting anything less than 1 so the
+/// bootstrap in `rt::run` is never left trying to build zero schedulers.
+fn parse_nthreads(s: &str) -> Option<uint> {
+    match from_str::<uint>(s) {
+        Some(n) if n >= 1 => Some(n),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_nthreads, parse_rtio_backend, Uv, Null};
+    use option::{Some, None};
+
+    #[test]
+    fn parse_nthreads_accepts_positive_values() {
+        assert_eq!(parse_nthreads("4"), Some(4));
+        assert_eq!(parse_nthreads("1"), Some(1));
+    }
+
+    #[test]
+    fn parse_nthreads_rejects_zero_and_garbage() {
+        assert_eq!(parse_nthreads("0"), None);
+        assert_eq!(parse_nthreads("-1"), None);
+        assert_eq!(parse_nthreads("not a number"), None);
+        assert_eq!(parse_nthreads(""), None);
+    }
+
+    #[test]
+    fn rtio_backend_defaults_to_uv() {
+        assert_eq!(parse_rtio_backend(None), Uv);
+        assert_eq!(parse_rtio_backend(Some("uv")), Uv);
+        assert_eq!(parse_rtio_backend(Some("bogus")), Uv);
+    }
+
+    #[test]
+    fn rtio_backend_selects_null() {
+        assert_eq!(parse_rtio_backend(Some("null")), Null);
+    }
+}
@@ -71,8 +71,7 @@ use rt::sched::{Scheduler, Shutdown};
 use rt::sleeper_list::SleeperList;
 use rt::task::Task;
 use rt::thread::Thread;
-use rt::work_queue::WorkQueue;
-use rt::uv::uvio::UvEventLoop;
+use rt::work_queue::Deque;
 use unstable::atomics::{AtomicInt, SeqCst};
 use unstable::sync::UnsafeAtomicRcBox;
 use vec::{OwnedVector, MutableVector};
@@ -104,7 +103,7 @@ pub mod uv;
 /// or task-local storage.
 pub mod local;
 
-/// A parallel work-stealing deque.
+/// A Chase-Lev work-stealing deque, one per scheduler.
 mod work_queue;
 
 /// A parallel queue.
@@ -220,13 +219,34 @@ pub fn run(main: ~fn()) -> int {
 
     static DEFAULT_ERROR_CODE: int = 101;
 
-    let nthreads = util::default_sched_threads();
+    // Read once, up front: how many scheduler threads to run and
+    // whether to pin each one to its own core. An explicit
+    // `RUST_THREADS` overrides the one-per-core default; affinity is
+    // off unless `RUST_SCHED_AFFINITY` asks for it.
+    let sched_config = env::sched_config();
+    let nthreads = sched_config.nthreads.get_or_default(util::default_sched_threads());
+
+    // Chosen once for the whole run: every scheduler is driven by a loop
+    // built by this factory, defaulting to libuv but overridable (e.g.
+    // to a no-I/O loop for compute-only embedders) via `RUST_RTIO`.
+    let loop_factory = env::event_loop_factory();
 
     // The shared list of sleeping schedulers. Schedulers wake each other
     // occassionally to do new work.
     let sleepers = SleeperList::new();
-    // The shared work queue. Temporary until work stealing is implemented.
-    let work_queue = WorkQueue::new();
+
+    // Each scheduler gets its own work-stealing deque. A scheduler only
+    // ever pushes and pops from its own deque; the `Stealer` handles for
+    // every *other* deque are handed to it so that when its own deque
+    // runs dry it can try to steal from a randomly chosen victim before
+    // registering itself as a sleeper.
+    let mut deques = ~[];
+    let mut stealers = ~[];
+    for nthreads.times {
+        let deque = Deque::new();
+        stealers.push(deque.steal_handle());
+        deques.push(deque);
+    }
 
     // The schedulers.
     let mut scheds = ~[];
@@ -234,14 +254,28 @@ pub fn run(main: ~fn()) -> int {
     // sent the Shutdown message to terminate the schedulers.
     let mut handles = ~[];
 
+    let mut i = 0;
     for nthreads.times {
-        // Every scheduler is driven by an I/O event loop.
-        let loop_ = ~UvEventLoop::new();
-        let mut sched = ~Scheduler::new(loop_, work_queue.clone(), sleepers.clone());
+        // Every scheduler is driven by an I/O event loop, built by
+        // whichever factory `RUST_RTIO` selected above.
+        let loop_ = loop_factory.new_loop();
+        let deque = deques.shift();
+        // Every *other* scheduler's stealer handle, i.e. everyone's but
+        // this one's own deque -- stealing from yourself is pointless
+        // and would just waste a steal attempt that could have gone to
+        // an actual victim.
+        let mut victims = ~[];
+        for stealers.iter().enumerate().advance |(j, s)| {
+            if j != i {
+                victims.push(s.clone());
+            }
+        }
+        let mut sched = ~Scheduler::new(loop_, deque, victims, sleepers.clone(), i);
         let handle = sched.make_handle();
 
         scheds.push(sched);
         handles.push(handle);
+        i += 1;
     }
 
     // Create a shared cell for transmitting the process exit
@@ -280,15 +314,21 @@ pub fn run(main: ~fn()) -> int {
     main_task.death.on_exit = Some(on_exit);
     scheds[0].enqueue_task(main_task);
 
-    // Run each scheduler in a thread.
+    // Run each scheduler in a thread, pinning it to its own core first
+    // when the affinity policy calls for it.
+    let affinity = sched_config.affinity;
     let mut threads = ~[];
     while !scheds.is_empty() {
+        // The core a scheduler lands on is just its position in the
+        // (already-built) scheduler list; stable and simple.
+        let cpu = if affinity { Some(scheds.len() - 1) } else { None };
         let sched = scheds.pop();
         let sched_cell = Cell::new(sched);
-        let thread = do Thread::start {
+        let runner: ~fn() = || {
             let sched = sched_cell.take();
             sched.run();
         };
+        let thread = Thread::start_on_cpu(cpu, runner);
 
         threads.push(thread);
     }
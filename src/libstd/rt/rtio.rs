@@ -0,0 +1,82 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The `EventLoop` trait that every scheduler is driven by, and the
+//! `EventLoopFactory` abstraction used to pick an implementation at
+//! startup without hard-wiring libuv into `rt::run`.
+
+/// The event loop that drives a single scheduler. Implementations are
+/// responsible for running callbacks registered on them and returning
+/// once there is no more outstanding work (I/O or otherwise) to wait on.
+pub trait EventLoop {
+    fn run(&mut self);
+    fn callback(&mut self, f: ~fn());
+    fn pausible_idle_callback(&mut self) -> ~PausibleIdleCallback;
+}
+
+/// A callback that can be paused and resumed; used by a scheduler to be
+/// woken when it has no more work and is about to go idle.
+pub trait PausibleIdleCallback {
+    fn start(&mut self, f: ~fn());
+    fn pause(&mut self);
+    fn resume(&mut self);
+}
+
+/// Constructs the `EventLoop` that a single scheduler will be driven by.
+///
+/// `rt::run` asks one `EventLoopFactory` to build one loop per scheduler
+/// thread, rather than hard-coding `~UvEventLoop::new()`. This lets an
+/// embedder that doesn't want the libuv dependency, or that wants to
+/// experiment with another I/O subsystem, swap the implementation
+/// without touching scheduler code. See `rt::env::event_loop_factory`
+/// for how the choice is made.
+pub trait EventLoopFactory {
+    fn new_loop(&self) -> ~EventLoop;
+}
+
+/// A minimal event loop for compute-only workloads that never touch
+/// (synchronous) I/O. `run` returns immediately, since there is never
+/// anything to wait on; it exists so a scheduler can be built without
+/// linking libuv at all.
+pub struct NullEventLoop;
+
+impl EventLoop for NullEventLoop {
+    fn run(&mut self) {
+        // Nothing to wait on: tasks are driven entirely by the
+        // scheduler's own work-stealing deque, not by this loop.
+    }
+
+    fn callback(&mut self, f: ~fn()) {
+        // No queue to defer to; run it immediately.
+        f();
+    }
+
+    fn pausible_idle_callback(&mut self) -> ~PausibleIdleCallback {
+        ~NullIdleCallback as ~PausibleIdleCallback
+    }
+}
+
+struct NullIdleCallback;
+
+impl PausibleIdleCallback for NullIdleCallback {
+    fn start(&mut self, _f: ~fn()) {}
+    fn pause(&mut self) {}
+    fn resume(&mut self) {}
+}
+
+/// Builds `NullEventLoop`s. Selected via `RUST_RTIO` for compute-only
+/// embedders who don't want the libuv dependency.
+pub struct NullEventLoopFactory;
+
+impl EventLoopFactory for NullEventLoopFactory {
+    fn new_loop(&self) -> ~EventLoop {
+        ~NullEventLoop as ~EventLoop
+    }
+}
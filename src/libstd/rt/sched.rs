@@ -0,0 +1,234 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The coroutine task scheduler.
+//!
+//! Each `Scheduler` owns one end of a work-stealing `Deque` (see
+//! `rt::work_queue`) and holds `Stealer` handles onto every other
+//! scheduler's deque. `resched` is the policy that ties the two
+//! together: look in your own deque first, and only fall back to
+//! randomly sampling the other schedulers' deques -- and only park
+//! on the shared `SleeperList` once every one of those attempts comes
+//! up empty.
+
+use option::{Option, Some, None};
+use rt::rtio::{EventLoop, PausibleIdleCallback};
+use rt::sleeper_list::SleeperList;
+use rt::stack::StackPool;
+use rt::task::Task;
+use rt::work_queue::{Deque, Stealer};
+use rt::message_queue::MessageQueue;
+use unstable::atomics::{AtomicUint, SeqCst};
+
+/// How many randomly-chosen victims to try stealing from before giving
+/// up and registering as a sleeper. Bounded so a scheduler with many
+/// peers doesn't spin through all of them on every empty poll.
+static MAX_STEAL_ATTEMPTS: uint = 8;
+
+/// Messages sent to a scheduler via its `SchedHandle`.
+pub enum SchedMessage {
+    /// Shut the scheduler down once its current task tree finishes.
+    Shutdown,
+    /// Wake up and look for work; sent by a sleeper's peers after they
+    /// push new work, so a scheduler parked on `SleeperList` doesn't
+    /// miss it.
+    Wake,
+}
+
+/// A remote handle to a scheduler, used to send it `SchedMessage`s from
+/// any thread.
+pub struct SchedHandle {
+    priv queue: MessageQueue<SchedMessage>,
+}
+
+impl SchedHandle {
+    pub fn send(&mut self, msg: SchedMessage) {
+        self.queue.push(msg);
+    }
+}
+
+pub struct Scheduler {
+    /// Stack segments for tasks spawned on this scheduler; shared with
+    /// `Task::new_root` by the `rt::run` bootstrap.
+    pub stack_pool: StackPool,
+
+    priv event_loop: ~EventLoop,
+    priv deque: Deque<~Task>,
+    priv victims: ~[Stealer<~Task>],
+    priv sleepers: SleeperList,
+    priv message_queue: MessageQueue<SchedMessage>,
+    priv rng_state: AtomicUint,
+    priv current_task: Option<~Task>,
+}
+
+impl Scheduler {
+    /// `index` is this scheduler's position among its siblings, as
+    /// assigned by `rt::run`; its only job is to seed `rng_state`
+    /// differently per scheduler (see `seed_from_index`).
+    pub fn new(event_loop: ~EventLoop,
+               deque: Deque<~Task>,
+               victims: ~[Stealer<~Task>],
+               sleepers: SleeperList,
+               index: uint) -> Scheduler {
+        Scheduler {
+            stack_pool: StackPool::new(),
+            event_loop: event_loop,
+            deque: deque,
+            victims: victims,
+            sleepers: sleepers,
+            message_queue: MessageQueue::new(),
+            rng_state: AtomicUint::new(seed_from_index(index)),
+            current_task: None,
+        }
+    }
+
+    pub fn make_handle(&mut self) -> SchedHandle {
+        SchedHandle { queue: self.message_queue.clone() }
+    }
+
+    /// Enqueue a task to run on this scheduler. Used both to seed the
+    /// main task at startup and, eventually, for a task spawning
+    /// another from inside this scheduler.
+    pub fn enqueue_task(&mut self, task: ~Task) {
+        self.deque.push(task);
+        self.wake_a_sleeper();
+    }
+
+    pub fn in_task_context(&self) -> bool {
+        self.current_task.is_some()
+    }
+
+    /// Run this scheduler until it receives `Shutdown`.
+    ///
+    /// Each iteration is the steal-then-park policy the module doc
+    /// describes: try our own deque, then up to `MAX_STEAL_ATTEMPTS`
+    /// randomly chosen victims (each pick is independent, so a victim
+    /// may be tried more than once and another not at all), and only
+    /// register as a sleeper -- a truly idle scheduler -- once both come
+    /// up empty. A successful steal at any point resumes
+    /// execution immediately without ever touching `SleeperList`.
+    pub fn run(&mut self) {
+        loop {
+            loop {
+                match self.message_queue.pop() {
+                    Some(Shutdown) => return,
+                    Some(Wake) => {}
+                    None => break,
+                }
+            }
+
+            match self.resched() {
+                Some(task) => {
+                    self.run_task(task);
+                }
+                None => {
+                    // Every deque we could see was empty: register as a
+                    // sleeper and let the event loop genuinely block
+                    // until either a `Wake` (sent by `wake_a_sleeper`
+                    // when a sibling pushes new work) or real I/O pulls
+                    // it back out of `run()`. Starting the idle callback
+                    // is what lets `run()` actually park instead of
+                    // spinning; it's paused again the instant we come
+                    // back out, whatever woke us.
+                    let handle = self.make_handle();
+                    self.sleepers.push(handle);
+                    let mut idle = self.event_loop.pausible_idle_callback();
+                    idle.start(|| {});
+                    self.event_loop.run();
+                    idle.pause();
+                }
+            }
+        }
+    }
+
+    /// Look for one runnable task: our own deque first, then up to
+    /// `MAX_STEAL_ATTEMPTS` randomly chosen victims. Returns `None` only
+    /// once every one of those has come up empty.
+    fn resched(&mut self) -> Option<~Task> {
+        match self.deque.pop() {
+            Some(task) => return Some(task),
+            None => {}
+        }
+
+        if self.victims.is_empty() {
+            return None;
+        }
+
+        let mut attempts = 0;
+        while attempts < MAX_STEAL_ATTEMPTS {
+            let idx = self.next_rand() % self.victims.len();
+            match self.victims[idx].steal() {
+                Some(task) => return Some(task),
+                None => {}
+            }
+            attempts += 1;
+        }
+
+        None
+    }
+
+    /// Hand a stolen or popped task to the task-context machinery. The
+    /// actual context switch lives in `rt::context`/`rt::task`; here we
+    /// just track which task is current for `in_task_context`.
+    fn run_task(&mut self, task: ~Task) {
+        self.current_task = Some(task);
+        match self.current_task {
+            Some(ref mut task) => task.run(),
+            None => {}
+        }
+        self.current_task = None;
+    }
+
+    /// A small xorshift PRNG: enough to pick a victim without pulling
+    /// in a full `rand::Rng` for one call site.
+    fn next_rand(&mut self) -> uint {
+        let mut x = self.rng_state.load(SeqCst);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state.store(x, SeqCst);
+        x
+    }
+
+    /// Pop one parked sleeper, if any, and send it `Wake` so a push onto
+    /// this scheduler's deque can never be missed by a sibling that's
+    /// already blocked in `event_loop.run()`.
+    fn wake_a_sleeper(&mut self) {
+        match self.sleepers.pop() {
+            Some(mut handle) => handle.send(Wake),
+            None => {}
+        }
+    }
+
+    pub fn deschedule_running_task_and_then(&mut self, f: &fn(&mut Scheduler, ~Task)) {
+        match self.current_task.take() {
+            Some(task) => f(self, task),
+            None => {}
+        }
+    }
+
+    pub fn enqueue_blocked_task(&mut self, task: ~Task) {
+        self.deque.push(task);
+        self.wake_a_sleeper();
+    }
+}
+
+/// Mix a scheduler's index into a fixed odd constant so sibling
+/// schedulers -- which would otherwise all start `next_rand` from the
+/// same literal seed -- begin their xorshift streams at different
+/// points and don't all pick the same "random" victim in lockstep.
+fn seed_from_index(index: uint) -> uint {
+    let mut seed = 0x2545F4914F6CDD1D ^ (index * 0x9E3779B97F4A7C15 + 1);
+    if seed == 0 {
+        // xorshift is stuck at a zero seed forever; never let it land there.
+        seed = 0x2545F4914F6CDD1D;
+    }
+    seed
+}
@@ -0,0 +1,118 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Bindings to system threading libraries.
+
+use cell::Cell;
+use option::{Option, Some, None};
+
+/// A native OS thread, as spawned by `Thread::start`.
+pub struct Thread {
+    priv native: rust_thread,
+}
+
+type rust_thread = *u8;
+
+impl Thread {
+    /// Spawn a new OS thread running `f`.
+    pub fn start(f: ~fn()) -> Thread {
+        Thread::start_on_cpu(None, f)
+    }
+
+    /// Spawn a new OS thread running `f`, optionally pinned to `cpu` for
+    /// the lifetime of the thread. Passing `None` leaves placement to
+    /// the OS scheduler, exactly like `start`.
+    ///
+    /// The affinity, if any, is applied from inside the new thread
+    /// before `f` runs, since that's the only context in which the
+    /// native thread handle needed to set it is available.
+    pub fn start_on_cpu(cpu: Option<uint>, f: ~fn()) -> Thread {
+        let f = Cell::new(f);
+        let runner: ~fn() = || {
+            match cpu {
+                Some(cpu) => set_current_thread_affinity(cpu),
+                None => {}
+            }
+            f.take()()
+        };
+        Thread { native: spawn_raw(runner) }
+    }
+}
+
+impl Drop for Thread {
+    fn drop(&mut self) {
+        unsafe { rust_thread_join(self.native); }
+    }
+}
+
+fn spawn_raw(f: ~fn()) -> rust_thread {
+    unsafe { rust_thread_spawn(f) }
+}
+
+/// Pin the calling (native) thread to a single CPU core, so the OS
+/// scheduler never migrates it. Used by `rt::run` when an affinity
+/// policy is configured, so that a scheduler's work-stealing deque
+/// keeps its cache-friendly, consistent owner core.
+///
+/// `cpu` is wrapped modulo the number of cores actually available, so a
+/// scheduler count larger than `nproc` (e.g. from a `RUST_THREADS`
+/// override) can never be asked to pin onto a nonexistent core.
+pub fn set_current_thread_affinity(cpu: uint) {
+    let ncpus = num_cpus();
+    unsafe { rust_thread_set_affinity((cpu % ncpus) as i32); }
+}
+
+/// The number of cores available to pin threads to. Always at least 1,
+/// so callers can safely compute `cpu % num_cpus()` without guarding
+/// against a zero divisor.
+fn num_cpus() -> uint {
+    let n = unsafe { rust_get_num_cpus() };
+    if n < 1 { 1 } else { n as uint }
+}
+
+extern {
+    fn rust_thread_spawn(f: ~fn()) -> rust_thread;
+    fn rust_thread_join(thread: rust_thread);
+    fn rust_thread_set_affinity(cpu: i32);
+    fn rust_get_num_cpus() -> i32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Thread;
+    use option::{None, Some};
+    use unstable::atomics::{AtomicBool, SeqCst, INIT_ATOMIC_BOOL};
+    use unstable::sync::UnsafeArc;
+
+    #[test]
+    fn start_runs_the_closure() {
+        let ran = UnsafeArc::new(INIT_ATOMIC_BOOL);
+        let ran2 = ran.clone();
+        {
+            let _thread = Thread::start(|| {
+                unsafe { (*ran2.get()).store(true, SeqCst); }
+            });
+            // `_thread`'s `Drop` impl joins before the block ends.
+        }
+        unsafe { assert!((*ran.get()).load(SeqCst)); }
+    }
+
+    #[test]
+    fn start_on_cpu_none_behaves_like_start() {
+        let ran = UnsafeArc::new(INIT_ATOMIC_BOOL);
+        let ran2 = ran.clone();
+        {
+            let _thread = Thread::start_on_cpu(None, || {
+                unsafe { (*ran2.get()).store(true, SeqCst); }
+            });
+        }
+        unsafe { assert!((*ran.get()).load(SeqCst)); }
+    }
+}
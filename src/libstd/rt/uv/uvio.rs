@@ -0,0 +1,60 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The default `rtio::EventLoop` and its factory.
+
+use rt::rtio::{EventLoop, EventLoopFactory, PausibleIdleCallback};
+
+/// The default event loop. It holds no libuv `uv_loop_t`: `run` returns
+/// immediately and `callback` runs its closure at once, so it drives no
+/// I/O.
+pub struct UvEventLoop {
+    priv idle_stopped: bool,
+}
+
+impl UvEventLoop {
+    pub fn new() -> UvEventLoop {
+        UvEventLoop { idle_stopped: true }
+    }
+}
+
+impl EventLoop for UvEventLoop {
+    fn run(&mut self) {
+        // No `uv_loop_t` to drive; returns immediately.
+    }
+
+    fn callback(&mut self, f: ~fn()) {
+        f();
+    }
+
+    fn pausible_idle_callback(&mut self) -> ~PausibleIdleCallback {
+        ~UvIdleCallback { active: false } as ~PausibleIdleCallback
+    }
+}
+
+struct UvIdleCallback {
+    active: bool,
+}
+
+impl PausibleIdleCallback for UvIdleCallback {
+    fn start(&mut self, _f: ~fn()) { self.active = true; }
+    fn pause(&mut self) { self.active = false; }
+    fn resume(&mut self) { self.active = true; }
+}
+
+/// Builds `UvEventLoop`s. The default, used unless `RUST_RTIO` selects
+/// an alternative backend; see `rt::env::event_loop_factory`.
+pub struct UvEventLoopFactory;
+
+impl EventLoopFactory for UvEventLoopFactory {
+    fn new_loop(&self) -> ~EventLoop {
+        ~UvEventLoop::new() as ~EventLoop
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2013 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A Chase-Lev work-stealing deque.
+//!
+//! Each scheduler owns exactly one `Deque`. The owner calls `push` and
+//! `pop` from the "bottom" of the deque with no locking required. Every
+//! other scheduler holds a `Stealer` handle, cloned from the owner's
+//! deque, and calls `steal` to take work from the "top". Only the owner
+//! may push or pop; any number of stealers may call `steal` concurrently
+//! with the owner and with each other.
+//!
+//! This replaces the single shared `WorkQueue` that every scheduler used
+//! to contend on: pushes and pops in the common case (no stealing
+//! happening) require no synchronization at all beyond a couple of
+//! atomic loads, and a failed steal just means the victim was empty or
+//! raced with another stealer.
+
+use cast;
+use clone::Clone;
+use option::{Option, Some, None};
+use unstable::atomics::{AtomicInt, AtomicPtr, SeqCst};
+use unstable::sync::UnsafeArc;
+use vec;
+
+static MIN_BUFFER_LOG_SIZE: int = 7;
+
+/// The backing storage for a `Deque`/`Stealer` pair. Every slot is
+/// logically uninitialized until a `put` writes to it; `vec::raw::set_len`
+/// is used to grow `storage` to its full size up front so that `get`/`put`
+/// can index straight into it and pass `~[T]`'s length-based bounds check
+/// (which would otherwise always see length 0, since nothing is ever
+/// pushed through the `Vec` API itself).
+struct Buffer<T> {
+    storage: ~[T],
+    log_size: int,
+}
+
+impl<T: Send> Buffer<T> {
+    fn new(log_size: int) -> Buffer<T> {
+        let size = 1 << log_size;
+        let mut storage: ~[T] = vec::with_capacity(size);
+        unsafe { vec::raw::set_len(&mut storage, size); }
+        Buffer { storage: storage, log_size: log_size }
+    }
+
+    fn size(&self) -> int { self.storage.len() as int }
+
+    unsafe fn get(&self, i: int) -> T {
+        let ptr: *T = &self.storage[i & (self.size() - 1)];
+        cast::transmute_copy(&*ptr)
+    }
+
+    unsafe fn put(&mut self, i: int, t: T) {
+        let ptr: *mut T = &mut self.storage[i & (self.size() - 1)];
+        cast::overwrite(ptr, t);
+    }
+
+    /// Build a buffer twice the size of `self`, copying across every live
+    /// element in `[top, bottom)`. The old buffer is intentionally left
+    /// behind rather than freed: a concurrent `steal` may still be
+    /// dereferencing it, and Chase-Lev never reclaims retired buffers for
+    /// exactly that reason.
+    unsafe fn grow(&self, bottom: int, top: int) -> ~Buffer<T> {
+        let mut grown = ~Buffer::new(self.log_size + 1);
+        let mut i = top;
+        while i < bottom {
+            grown.put(i, self.get(i));
+            i += 1;
+        }
+        grown
+    }
+}
+
+struct DequeState<T> {
+    bottom: AtomicInt,
+    top: AtomicInt,
+    buffer: AtomicPtr<Buffer<T>>,
+}
+
+/// The owner-side handle into a scheduler's work-stealing deque.
+///
+/// Only the scheduler that created this `Deque` should `push` or `pop`
+/// from it; the matching `Stealer` handles, one per foreign scheduler,
+/// are produced by `steal_handle`.
+pub struct Deque<T> {
+    priv state: UnsafeArc<DequeState<T>>,
+}
+
+/// A handle letting a foreign scheduler steal work from someone else's
+/// `Deque`. Safe to clone and send to any number of other schedulers.
+pub struct Stealer<T> {
+    priv state: UnsafeArc<DequeState<T>>,
+}
+
+impl<T: Send> Deque<T> {
+    pub fn new() -> Deque<T> {
+        let buf = ~Buffer::new(MIN_BUFFER_LOG_SIZE);
+        let state = DequeState {
+            bottom: AtomicInt::new(0),
+            top: AtomicInt::new(0),
+            buffer: AtomicPtr::new(unsafe { cast::transmute(buf) }),
+        };
+        Deque { state: UnsafeArc::new(state) }
+    }
+
+    /// Produce a `Stealer` that other schedulers can use to steal from
+    /// this deque. May be called any number of times.
+    pub fn steal_handle(&self) -> Stealer<T> {
+        Stealer { state: self.state.clone() }
+    }
+
+    /// Push a task onto the bottom of the deque. Only the owner may call
+    /// this; never races with `pop`, only with concurrent `steal`s.
+    ///
+    /// Grows the backing buffer (doubling it) whenever it's full, so a
+    /// burst of pushes can never silently wrap and overwrite a live,
+    /// not-yet-executed task.
+    pub fn push(&mut self, t: T) {
+        unsafe {
+            let state = self.state.get();
+            let b = (*state).bottom.load(SeqCst);
+            let t_ = (*state).top.load(SeqCst);
+            let buf: &mut Buffer<T> = cast::transmute((*state).buffer.load(SeqCst));
+            if b - t_ >= buf.size() {
+                let grown = buf.grow(b, t_);
+                (*state).buffer.store(cast::transmute(grown), SeqCst);
+            }
+            let buf: &mut Buffer<T> = cast::transmute((*state).buffer.load(SeqCst));
+            buf.put(b, t);
+            (*state).bottom.store(b + 1, SeqCst);
+        }
+    }
+
+    /// Pop a task from the bottom of the deque. Returns `None` if the
+    /// deque is empty, in which case the caller should try `steal`ing
+    /// from a victim before parking itself.
+    pub fn pop(&mut self) -> Option<T> {
+        unsafe {
+            let state = self.state.get();
+            let b = (*state).bottom.load(SeqCst) - 1;
+            (*state).bottom.store(b, SeqCst);
+            let t = (*state).top.load(SeqCst);
+            if t > b {
+                // Deque was already empty; restore bottom and bail.
+                (*state).bottom.store(b + 1, SeqCst);
+                return None;
+            }
+            let buf: &mut Buffer<T> = cast::transmute((*state).buffer.load(SeqCst));
+            let value = buf.get(b);
+            if t == b {
+                // Last element: race with stealers for it.
+                if (*state).top.compare_and_swap(t, t + 1, SeqCst) != t {
+                    (*state).bottom.store(b + 1, SeqCst);
+                    return None;
+                }
+                (*state).bottom.store(b + 1, SeqCst);
+            }
+            Some(value)
+        }
+    }
+}
+
+impl<T: Send> Stealer<T> {
+    /// Attempt to steal a single task from the top of the victim's
+    /// deque. Returns `None` if the deque was empty or a concurrent
+    /// steal (or the owner's `pop`) won the race; the caller should
+    /// just try a different victim.
+    pub fn steal(&mut self) -> Option<T> {
+        unsafe {
+            let state = self.state.get();
+            let t = (*state).top.load(SeqCst);
+            let b = (*state).bottom.load(SeqCst);
+            if t >= b {
+                return None;
+            }
+            let buf: &mut Buffer<T> = cast::transmute((*state).buffer.load(SeqCst));
+            let value = buf.get(t);
+            if (*state).top.compare_and_swap(t, t + 1, SeqCst) != t {
+                // Lost the race with another stealer (or the owner).
+                return None;
+            }
+            Some(value)
+        }
+    }
+}
+
+impl<T: Send> Clone for Stealer<T> {
+    fn clone(&self) -> Stealer<T> {
+        Stealer { state: self.state.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Deque;
+    use option::{Some, None};
+
+    #[test]
+    fn push_pop_single_threaded() {
+        let mut deque = Deque::new();
+        assert!(deque.pop().is_none());
+        deque.push(1);
+        deque.push(2);
+        deque.push(3);
+        assert_eq!(deque.pop(), Some(3));
+        assert_eq!(deque.pop(), Some(2));
+        assert_eq!(deque.pop(), Some(1));
+        assert!(deque.pop().is_none());
+    }
+
+    #[test]
+    fn steal_from_the_top() {
+        let mut deque = Deque::new();
+        let mut stealer = deque.steal_handle();
+        assert!(stealer.steal().is_none());
+        deque.push(1);
+        deque.push(2);
+        assert_eq!(stealer.steal(), Some(1));
+        assert_eq!(deque.pop(), Some(2));
+        assert!(deque.pop().is_none());
+    }
+
+    #[test]
+    fn push_past_initial_capacity_grows_instead_of_wrapping() {
+        let mut deque = Deque::new();
+        let n = 1000;
+        for i in range(0, n) {
+            deque.push(i);
+        }
+        let mut popped = ~[];
+        loop {
+            match deque.pop() {
+                Some(i) => popped.push(i),
+                None => break,
+            }
+        }
+        popped.reverse();
+        assert_eq!(popped.len(), n as uint);
+        for i in range(0, n) {
+            assert_eq!(popped[i], i);
+        }
+    }
+}